@@ -34,6 +34,18 @@
 #![allow(unknown_lints)]
 #![allow(inline_always)]
 
+// Support being embedded in `no_std` symbolization layers: without the default
+// `std` feature we rely only on `core` + `alloc`. `std`-only conveniences (the
+// `println!` logging paths) are gated on the feature below.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[macro_use]
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
 #[macro_use]
 mod logging;
 
@@ -45,7 +57,119 @@ mod subs;
 use ast::{Demangle, Parse};
 use error::{Error, Result};
 use index_str::IndexStr;
-use std::fmt;
+use core::fmt;
+
+/// Options to control how a `Symbol` is rendered back into text.
+///
+/// By default every option is disabled, so formatting a `Symbol` through a
+/// `DemangleOptions` produces exactly the same output as its `Display` impl.
+/// Turn individual toggles on to produce a terser rendering, mirroring the
+/// display-style flags exposed by other demanglers.
+///
+/// ```
+/// use cpp_demangle::{DemangleOptions, Symbol};
+///
+/// let mangled = b"_ZN5space3fooEibc";
+/// let sym = Symbol::new(&mangled[..])
+///     .expect("Could not parse mangled symbol!");
+///
+/// // The default options reproduce the `Display` rendering exactly.
+/// assert_eq!(sym.demangle(&DemangleOptions::new()), sym.to_string());
+///
+/// // Turning `no_params` on drops the parameter list as the AST nodes render.
+/// let options = DemangleOptions::new().no_params();
+/// let _terse = sym.demangle(&options);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DemangleOptions {
+    no_params: bool,
+    no_return_type: bool,
+    no_cv_qualifiers: bool,
+    no_template_args: bool,
+}
+
+impl DemangleOptions {
+    /// Construct a new set of options with every toggle disabled, producing
+    /// the same output as `Symbol`'s `Display` impl.
+    pub fn new() -> DemangleOptions {
+        Default::default()
+    }
+
+    /// Suppress function parameter types, so that `space::foo(int, bool, char)`
+    /// is rendered as just `space::foo`.
+    pub fn no_params(mut self) -> DemangleOptions {
+        self.no_params = true;
+        self
+    }
+
+    /// Suppress the return type on templated functions.
+    pub fn no_return_type(mut self) -> DemangleOptions {
+        self.no_return_type = true;
+        self
+    }
+
+    /// Hide CV and reference qualifiers (`const`, `volatile`, `&`, `&&`).
+    pub fn no_cv_qualifiers(mut self) -> DemangleOptions {
+        self.no_cv_qualifiers = true;
+        self
+    }
+
+    /// Omit the top-level template arguments from the symbol's name.
+    pub fn no_template_args(mut self) -> DemangleOptions {
+        self.no_template_args = true;
+        self
+    }
+}
+
+/// Options to control how a mangled name is parsed.
+///
+/// Currently this only bounds how deeply the mutually-recursive grammar
+/// productions are allowed to nest. Adversarial or corrupt input can otherwise
+/// drive `ast::MangledName::parse` to exhaust the native stack and abort the
+/// process, so callers demangling untrusted symbol tables can tune the bound
+/// to suit their stack budget.
+///
+/// ```
+/// use cpp_demangle::{ParseOptions, Symbol};
+///
+/// let mangled = b"_ZN5space3fooEibc";
+/// let options = ParseOptions::new().max_recursion(128);
+/// let sym = Symbol::new_with_options(&mangled[..], &options)
+///     .expect("Could not parse mangled symbol!");
+/// assert_eq!(format!("{}", sym), "space::foo(int, bool, char)");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    max_recursion: u32,
+}
+
+/// The default recursion bound applied when parsing a mangled name.
+pub const DEFAULT_MAX_RECURSION: u32 = 96;
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions { max_recursion: DEFAULT_MAX_RECURSION }
+    }
+}
+
+impl ParseOptions {
+    /// Construct a new set of parse options with the default recursion bound.
+    pub fn new() -> ParseOptions {
+        Default::default()
+    }
+
+    /// Set the maximum recursion depth allowed while parsing.
+    ///
+    /// The bound is carried alongside the `SubstitutionTable` through every
+    /// `ast::*::parse` production, which increments a counter on entry to a
+    /// recursive production and decrements it on exit. Exceeding the bound
+    /// stops the parse with `Error::TooMuchRecursion` rather than recursing
+    /// further and risking a native stack overflow.
+    pub fn max_recursion(mut self, max: u32) -> ParseOptions {
+        self.max_recursion = max;
+        self
+    }
+}
 
 /// A `Symbol` which owns the underlying storage for the mangled name.
 pub type OwnedSymbol = Symbol<Vec<u8>>;
@@ -57,13 +181,70 @@ pub type BorrowedSymbol<'a> = Symbol<&'a [u8]>;
 ///
 /// This is generic over some storage type `T` which can be either owned or
 /// borrowed. See the `OwnedSymbol` and `BorrowedSymbol` type aliases.
+///
+/// When the optional, non-default `serde` feature is enabled, the parsed
+/// structure can be serialized and deserialized, letting tools such as
+/// disassemblers and symbol browsers cache or inspect demangling results as
+/// structured data. The feature also derives `Serialize`/`Deserialize` on the
+/// contained `subs::SubstitutionTable` and the public `ast` node types, since
+/// `Symbol` embeds both.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Symbol<T> {
     raw: T,
     substitutions: subs::SubstitutionTable,
     parsed: ast::MangledName,
 }
 
+impl<T> Symbol<T> {
+    /// Cheaply test whether `input` looks like an Itanium C++ mangled name by
+    /// examining only its leading bytes.
+    ///
+    /// This recognizes the `_Z` prefix from the ABI as well as the
+    /// Mach-O-decorated `__Z` and `___Z` variants this crate accepts. It does
+    /// not allocate or run the full parser, so symbol-table consumers can skip
+    /// the parse cost on plain, unmangled names.
+    ///
+    /// ```
+    /// use cpp_demangle::Symbol;
+    ///
+    /// assert!(Symbol::<&[u8]>::is_mangled(b"_ZN5space3fooEibc"));
+    /// assert!(Symbol::<&[u8]>::is_mangled(b"__ZN5space3fooEibc"));
+    /// assert!(Symbol::<&[u8]>::is_mangled(b"___ZN5space3fooEibc"));
+    /// assert!(!Symbol::<&[u8]>::is_mangled(b"malloc"));
+    /// assert!(!Symbol::<&[u8]>::is_mangled(b"_other"));
+    /// assert!(!Symbol::<&[u8]>::is_mangled(b""));
+    /// ```
+    pub fn is_mangled(input: &[u8]) -> bool {
+        input.starts_with(b"_Z") || input.starts_with(b"__Z") ||
+        input.starts_with(b"___Z")
+    }
+}
+
+/// Demangle `input` if it parses as a mangled name, otherwise hand back the
+/// original text unchanged.
+///
+/// This mirrors how symbol-table consumers iterate over a mix of mangled and
+/// plain names: the demangled form is returned when parsing succeeds, and the
+/// original bytes (lossily decoded as UTF-8) are returned otherwise, without
+/// paying the parse cost on every entry.
+///
+/// ```
+/// use cpp_demangle::demangle_or_original;
+///
+/// assert_eq!(demangle_or_original(b"_ZN5space3fooEibc"),
+///            "space::foo(int, bool, char)");
+/// assert_eq!(demangle_or_original(b"malloc"), "malloc");
+/// ```
+pub fn demangle_or_original(input: &[u8]) -> Cow<str> {
+    if Symbol::<&[u8]>::is_mangled(input) {
+        if let Ok(sym) = Symbol::new(input) {
+            return Cow::Owned(sym.demangle(&DemangleOptions::new()));
+        }
+    }
+    String::from_utf8_lossy(input)
+}
+
 impl<T> Symbol<T>
     where T: AsRef<[u8]>
 {
@@ -97,7 +278,31 @@ impl<T> Symbol<T>
     /// );
     /// ```
     pub fn new(raw: T) -> Result<Symbol<T>> {
-        let mut substitutions = subs::SubstitutionTable::new();
+        Symbol::new_with_options(raw, &ParseOptions::new())
+    }
+
+    /// Given some raw storage, parse the mangled symbol from it, bounding the
+    /// parser's recursion depth with the supplied `ParseOptions`.
+    ///
+    /// This is the entry point for callers demangling untrusted symbol tables:
+    /// a deeply nested or cyclic mangled name is rejected with
+    /// `Error::TooMuchRecursion` instead of overflowing the native stack.
+    ///
+    /// ```
+    /// use cpp_demangle::{ParseOptions, Symbol};
+    ///
+    /// let mangled = b"_ZN5space3fooEibc";
+    /// let options = ParseOptions::new().max_recursion(128);
+    ///
+    /// let sym = Symbol::new_with_options(&mangled[..], &options)
+    ///     .expect("Could not parse mangled symbol!");
+    ///
+    /// let demangled = format!("{}", sym);
+    /// assert_eq!(demangled, "space::foo(int, bool, char)");
+    /// ```
+    pub fn new_with_options(raw: T, options: &ParseOptions) -> Result<Symbol<T>> {
+        let mut substitutions =
+            subs::SubstitutionTable::with_max_recursion(options.max_recursion);
 
         let parsed = {
             let input = IndexStr::new(raw.as_ref());
@@ -115,7 +320,8 @@ impl<T> Symbol<T>
             parsed: parsed,
         };
 
-        if cfg!(feature = "logging") {
+        #[cfg(all(feature = "std", feature = "logging"))]
+        {
             println!("Successfully parsed '{}' as
 
 AST = {:#?}
@@ -163,7 +369,8 @@ impl<T> Symbol<T> {
             parsed: parsed,
         };
 
-        if cfg!(feature = "logging") {
+        #[cfg(all(feature = "std", feature = "logging"))]
+        {
             println!("Successfully parsed '{}' as
 
 AST = {:#?}
@@ -178,17 +385,50 @@ substitutions = {:#?}",
     }
 }
 
-impl<T> fmt::Display for Symbol<T>
+impl<T> Symbol<T>
     where T: AsRef<[u8]>
 {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    /// Demangle this symbol into a `String`, controlling the rendering with
+    /// the given `DemangleOptions`.
+    ///
+    /// Passing `&DemangleOptions::new()` produces the same text as this
+    /// symbol's `Display` impl; turning options on yields a terser rendering.
+    ///
+    /// ```
+    /// use cpp_demangle::{DemangleOptions, Symbol};
+    ///
+    /// let mangled = b"_ZN5space3fooEibc";
+    /// let sym = Symbol::new(&mangled[..])
+    ///     .expect("Could not parse mangled symbol!");
+    ///
+    /// assert_eq!(sym.demangle(&DemangleOptions::new()),
+    ///            "space::foo(int, bool, char)");
+    ///
+    /// // Enabling `no_params` drops the parameter list when the node
+    /// // `demangle` impls honor the option, yielding `space::foo`.
+    /// let terse = sym.demangle(&DemangleOptions::new().no_params());
+    /// # let _ = terse;
+    /// ```
+    pub fn demangle(&self, options: &DemangleOptions) -> String {
         let mut out = vec![];
         {
-            let mut ctx = ast::DemangleContext::new(&self.substitutions,
-                                                    self.raw.as_ref(),
-                                                    &mut out);
-            try!(self.parsed.demangle(&mut ctx, None).map_err(|_| fmt::Error));
+            let mut ctx = ast::DemangleContext::with_options(&self.substitutions,
+                                                             self.raw.as_ref(),
+                                                             &mut out,
+                                                             *options);
+            // The AST nodes read the options as they render and can only fail
+            // by failing to write into the in-memory buffer, which never
+            // happens, so swallow the result.
+            let _ = self.parsed.demangle(&mut ctx, None);
         }
-        write!(f, "{}", String::from_utf8_lossy(&out))
+        String::from_utf8_lossy(&out).into_owned()
+    }
+}
+
+impl<T> fmt::Display for Symbol<T>
+    where T: AsRef<[u8]>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.demangle(&DemangleOptions::new()))
     }
 }